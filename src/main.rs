@@ -1,11 +1,16 @@
 use anyhow::{Context, Result};
 use binaryornot::is_binary;
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+use filetime::FileTime;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::WalkBuilder;
 use log::{debug, error, info, trace, warn};
-use walkdir::WalkDir;
+use rayon::prelude::*;
 use std::{
+    ffi::OsString,
     fs,
     path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
 };
 
 /// A CLI tool to convert CRLF line endings to LF in text files
@@ -23,6 +28,116 @@ struct Args {
     /// Dry run mode - show what would be changed without modifying files
     #[arg(short = 'n', long, default_value_t = false)]
     dry_run: bool,
+
+    /// Don't honor .gitignore, .ignore, or global git excludes when walking directories
+    #[arg(long, default_value_t = false)]
+    no_ignore: bool,
+
+    /// Include hidden files and directories (skipped by default)
+    #[arg(long, default_value_t = false)]
+    hidden: bool,
+
+    /// Preserve the original file's modification time on converted files
+    #[arg(long, default_value_t = false)]
+    preserve_mtime: bool,
+
+    /// Number of worker threads to use when processing a directory (default: logical CPU count)
+    #[arg(short = 'j', long)]
+    jobs: Option<usize>,
+
+    /// Follow symlinks while walking directories (cycles are detected and abort with an error)
+    #[arg(long, default_value_t = false)]
+    follow_symlinks: bool,
+
+    /// Target line ending to normalize files to
+    #[arg(long, value_enum, default_value_t = LineEnding::Lf)]
+    to: LineEnding,
+
+    /// Only process files matching this glob (repeatable)
+    #[arg(long = "include")]
+    include: Vec<String>,
+
+    /// Skip files matching this glob (repeatable)
+    #[arg(long = "exclude")]
+    exclude: Vec<String>,
+
+    /// Bypass binary detection for files matching --include
+    #[arg(long, default_value_t = false)]
+    force_text: bool,
+}
+
+/// The line ending convention files are normalized to.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum LineEnding {
+    /// Unix-style `\n`
+    Lf,
+    /// Windows-style `\r\n`
+    Crlf,
+    /// Classic Mac-style `\r`
+    Cr,
+}
+
+impl LineEnding {
+    /// The raw bytes this ending is written as.
+    fn as_bytes(self) -> &'static [u8] {
+        match self {
+            LineEnding::Lf => b"\n",
+            LineEnding::Crlf => b"\r\n",
+            LineEnding::Cr => b"\r",
+        }
+    }
+}
+
+impl Args {
+    /// The resolved worker count: the explicit `--jobs` value, or the logical CPU count.
+    fn jobs(&self) -> usize {
+        self.jobs
+            .unwrap_or_else(|| std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1))
+    }
+}
+
+/// Compiled `--include`/`--exclude` glob matchers, checked before any file is read.
+struct Filters {
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+    force_text: bool,
+}
+
+impl Filters {
+    fn from_args(args: &Args) -> Result<Self> {
+        Ok(Self {
+            include: Self::build_set(&args.include)?,
+            exclude: Self::build_set(&args.exclude)?,
+            force_text: args.force_text,
+        })
+    }
+
+    fn build_set(patterns: &[String]) -> Result<Option<GlobSet>> {
+        if patterns.is_empty() {
+            return Ok(None);
+        }
+
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            builder.add(Glob::new(pattern).with_context(|| format!("Invalid glob pattern: {pattern}"))?);
+        }
+
+        Ok(Some(builder.build().context("Failed to build glob matcher")?))
+    }
+
+    /// Whether `path` passes both the include and exclude filters.
+    fn allows(&self, path: &Path) -> bool {
+        if self.exclude.as_ref().is_some_and(|set| set.is_match(path)) {
+            return false;
+        }
+
+        self.include.as_ref().is_none_or(|set| set.is_match(path))
+    }
+
+    /// Whether binary detection should be skipped for `path` via `--force-text`.
+    fn forces_text(&self, path: &Path) -> bool {
+        self.force_text && self.include.as_ref().is_some_and(|set| set.is_match(path))
+    }
 }
 
 fn main() {
@@ -32,18 +147,38 @@ fn main() {
     let args = Args::parse();
 
     info!("Starting CRLF to LF conversion");
-    info!("Options: recursive={recursive}, dry_run={dry_run}", recursive = args.recursive, dry_run = args.dry_run);
+    info!(
+        "Options: recursive={recursive}, dry_run={dry_run}, no_ignore={no_ignore}, hidden={hidden}, jobs={jobs}, follow_symlinks={follow_symlinks}, to={to:?}, include={include}, exclude={exclude}, force_text={force_text}",
+        recursive = args.recursive,
+        dry_run = args.dry_run,
+        no_ignore = args.no_ignore,
+        hidden = args.hidden,
+        jobs = args.jobs(),
+        follow_symlinks = args.follow_symlinks,
+        to = args.to,
+        include = args.include.len(),
+        exclude = args.exclude.len(),
+        force_text = args.force_text
+    );
     info!("Processing {} path(s)", args.paths.len());
 
-    let mut total_files = 0;
-    let mut converted_files = 0;
+    let filters = match Filters::from_args(&args) {
+        Ok(filters) => filters,
+        Err(e) => {
+            error!("Invalid filter configuration: {e}");
+            std::process::exit(1);
+        }
+    };
+
+    let total_files = AtomicUsize::new(0);
+    let converted_files = AtomicUsize::new(0);
     let mut errors = 0;
 
     // Process each path provided
     for path in &args.paths {
         debug!("Processing path: {}", path.display());
 
-        match process_path(path, &args, &mut total_files, &mut converted_files) {
+        match process_path(path, &args, &filters, &total_files, &converted_files) {
             Ok(_) => {
                 info!("Processed path: {}", path.display());
             }
@@ -55,6 +190,8 @@ fn main() {
     }
 
     // Summary
+    let total_files = total_files.load(Ordering::Relaxed);
+    let converted_files = converted_files.load(Ordering::Relaxed);
     info!("Conversion complete");
     info!("Total text files processed: {total_files}");
     info!("Files converted: {converted_files}");
@@ -66,19 +203,19 @@ fn main() {
 }
 
 /// Process a single path (file or directory)
-fn process_path(path: &Path, args: &Args, total_files: &mut usize, converted_files: &mut usize) -> Result<()> {
+fn process_path(path: &Path, args: &Args, filters: &Filters, total_files: &AtomicUsize, converted_files: &AtomicUsize) -> Result<()> {
     if !path.exists() {
         return Err(anyhow::anyhow!("Path does not exist: {}", path.display()));
     }
 
     if path.is_file() {
         trace!("Path is a file: {}", path.display());
-        if let Err(e) = process_file(path, args.dry_run, total_files, converted_files) {
+        if let Err(e) = process_file(path, args.dry_run, args.preserve_mtime, args.to, filters, total_files, converted_files) {
             error!("Error processing file {path}: {e}", path = path.display());
         }
     } else if path.is_dir() {
         trace!("Path is a directory: {}", path.display());
-        process_directory(path, args, total_files, converted_files)?;
+        process_directory(path, args, filters, total_files, converted_files)?;
     } else {
         warn!("Path is neither a file nor a directory: {}", path.display());
     }
@@ -87,110 +224,238 @@ fn process_path(path: &Path, args: &Args, total_files: &mut usize, converted_fil
 }
 
 /// Process all files in a directory
-fn process_directory(dir: &Path, args: &Args, total_files: &mut usize, converted_files: &mut usize) -> Result<()> {
+fn process_directory(dir: &Path, args: &Args, filters: &Filters, total_files: &AtomicUsize, converted_files: &AtomicUsize) -> Result<()> {
     debug!("Processing directory: {:?}", dir);
 
-    let walker = if args.recursive {
+    let mut builder = WalkBuilder::new(dir);
+    builder
+        .follow_links(args.follow_symlinks)
+        .hidden(!args.hidden)
+        .ignore(!args.no_ignore)
+        .git_ignore(!args.no_ignore)
+        .git_global(!args.no_ignore)
+        .git_exclude(!args.no_ignore);
+
+    if args.recursive {
         trace!("Walking directory recursively");
-        WalkDir::new(dir).follow_links(false)
     } else {
         trace!("Walking directory non-recursively (max_depth=1)");
-        WalkDir::new(dir).max_depth(1).follow_links(false)
-    };
+        builder.max_depth(Some(1));
+    }
 
-    for entry in walker {
+    let mut files = Vec::new();
+    for entry in builder.build() {
         match entry {
             Ok(entry) => {
                 let path = entry.path();
 
+                if entry.path_is_symlink() {
+                    if let Ok(target) = fs::canonicalize(path) {
+                        debug!("Symlink resolved: {} -> {}", path.display(), target.display());
+                    }
+                }
+
                 // Skip directories themselves, we only process files
                 if path.is_file() {
-                    trace!("Found file in directory: {}", path.display());
-                    if let Err(e) = process_file(path, args.dry_run, total_files, converted_files) {
-                        error!("Error processing file {path}: {e}", path = path.display());
+                    if !filters.allows(path) {
+                        trace!("Path excluded by include/exclude filters: {}", path.display());
+                        continue;
                     }
+
+                    trace!("Found file in directory: {}", path.display());
+                    files.push(path.to_path_buf());
                 }
             }
             Err(e) => {
+                // The walker detects symlink cycles itself (when following links) and reports
+                // them as an error instead of recursing forever; surface that as a hard failure
+                // rather than silently skipping the offending subtree.
+                if args.follow_symlinks && is_loop_error(&e) {
+                    return Err(anyhow::anyhow!("Symlink loop detected while walking {}: {e}", dir.display()));
+                }
+
                 warn!("Error walking directory entry: {e}");
             }
         }
     }
 
+    let jobs = args.jobs();
+    let process_one = |path: &PathBuf| {
+        if let Err(e) = process_file(path, args.dry_run, args.preserve_mtime, args.to, filters, total_files, converted_files) {
+            error!("Error processing file {path}: {e}", path = path.display());
+        }
+    };
+
+    if jobs <= 1 {
+        trace!("Processing {} file(s) sequentially", files.len());
+        files.iter().for_each(process_one);
+    } else {
+        trace!("Processing {} file(s) across {jobs} worker thread(s)", files.len());
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build()
+            .context("Failed to build worker thread pool")?;
+
+        pool.install(|| files.par_iter().for_each(process_one));
+    }
+
     Ok(())
 }
 
+/// Whether `err` is (or wraps) an `ignore::Error::Loop`, i.e. a symlink cycle.
+///
+/// Matches the structured variant instead of sniffing `Display` text, so this keeps
+/// working if the `ignore` crate ever rewords its loop error message.
+fn is_loop_error(err: &ignore::Error) -> bool {
+    match err {
+        ignore::Error::Loop { .. } => true,
+        ignore::Error::Partial(errs) => errs.iter().any(is_loop_error),
+        ignore::Error::WithLineNumber { err, .. } => is_loop_error(err),
+        ignore::Error::WithPath { err, .. } => is_loop_error(err),
+        ignore::Error::WithDepth { err, .. } => is_loop_error(err),
+        _ => false,
+    }
+}
+
 /// Process a single file
-fn process_file(path: &Path, dry_run: bool, total_files: &mut usize, converted_files: &mut usize) -> Result<()> {
-    // Check if this file is a text file
+fn process_file(
+    path: &Path,
+    dry_run: bool,
+    preserve_mtime: bool,
+    to: LineEnding,
+    filters: &Filters,
+    total_files: &AtomicUsize,
+    converted_files: &AtomicUsize,
+) -> Result<()> {
+    // Cheap glob checks up front, before touching the file, so large-tree scans stay fast
+    if !filters.allows(path) {
+        trace!("Path excluded by include/exclude filters: {}", path.display());
+        return Ok(());
+    }
+
+    // Check if this file is a text file, unless --force-text overrides detection for it
     trace!("Checking if file is text: {}", path.display());
-    if is_binary(path)? {
+    if !filters.forces_text(path) && is_binary(path)? {
         trace!("File is not a text file, skipping: {}", path.display());
         return Ok(());
     }
 
     debug!("Processing text file: {}", path.display());
-    *total_files += 1;
+    total_files.fetch_add(1, Ordering::Relaxed);
 
     // Read file content
     let content = fs::read(path).with_context(|| format!("Failed to read file: {}", path.display()))?;
     trace!("Read {} bytes from file", content.len());
 
-    // Check if the file contains CRLF
-    if !content.windows(2).any(|w| w == b"\r\n") {
-        trace!("File does not contain CRLF, skipping: {}", path.display());
-        return Ok(());
-    }
-
-    debug!("File contains CRLF line endings: {}", path.display());
+    // Normalize every line ending (\r\n, lone \r, lone \n) to the target convention
+    let converted = normalize_line_endings(&content, to);
 
-    // Convert CRLF to LF
-    let converted = convert_crlf_to_lf(&content);
-
-    if converted.len() == content.len() {
-        trace!("No changes after conversion (already LF only): {}", path.display());
+    if converted == content {
+        trace!("No changes after normalization: {}", path.display());
         return Ok(());
     }
 
-    let bytes_saved = content.len() - converted.len();
-    debug!("Conversion will reduce file size by {bytes_saved} bytes");
+    debug!("File needs line ending normalization: {}", path.display());
+
+    let size_delta = converted.len() as isize - content.len() as isize;
+    debug!("Conversion will change file size by {size_delta} bytes");
 
     if dry_run {
         info!(
-            "[DRY RUN] Would convert: {path} ({bytes_saved} bytes saved)",
+            "[DRY RUN] Would convert: {path} ({size_delta} bytes delta)",
             path = path.display()
         );
     } else {
-        // Write converted content back to the file
-        fs::write(path, &converted).with_context(|| format!("Failed to write file: {}", path.display()))?;
+        // If `path` is a symlink, write through it: resolve the real target and replace
+        // *that* file, so the link itself is left pointing at the (now-converted) target
+        // instead of being clobbered by the atomic rename.
+        let target_path = if path.is_symlink() {
+            let resolved = fs::canonicalize(path).with_context(|| format!("Failed to resolve symlink target: {}", path.display()))?;
+            debug!("Writing through symlink {} to target {}", path.display(), resolved.display());
+            resolved
+        } else {
+            path.to_path_buf()
+        };
+
+        let metadata = fs::metadata(path).with_context(|| format!("Failed to read metadata for: {}", path.display()))?;
+        let mtime = preserve_mtime.then(|| FileTime::from_last_modification_time(&metadata));
 
-        debug!("Converted: {path} ({bytes_saved} bytes saved)", path = path.display());
+        write_atomic(&target_path, &converted, &metadata)?;
+
+        if let Some(mtime) = mtime {
+            filetime::set_file_mtime(&target_path, mtime).with_context(|| format!("Failed to restore mtime on: {}", target_path.display()))?;
+        }
+
+        debug!("Converted: {path} ({size_delta} bytes delta)", path = path.display());
     }
 
-    *converted_files += 1;
+    converted_files.fetch_add(1, Ordering::Relaxed);
     Ok(())
 }
 
-/// Convert CRLF line endings to LF
-fn convert_crlf_to_lf(content: &[u8]) -> Vec<u8> {
-    trace!("Converting CRLF to LF in {} byte buffer", content.len());
+/// Write `content` to `target` atomically: write to a sibling temp file, copy over
+/// `metadata`'s permissions, then rename into place. The temp file is cleaned up if
+/// any step, the rename included, fails, so a failed conversion doesn't leave it behind.
+fn write_atomic(target: &Path, content: &[u8], metadata: &fs::Metadata) -> Result<()> {
+    let tmp_path = temp_path_for(target);
+
+    let result = fs::write(&tmp_path, content)
+        .with_context(|| format!("Failed to write temp file: {}", tmp_path.display()))
+        .and_then(|_| {
+            fs::set_permissions(&tmp_path, metadata.permissions())
+                .with_context(|| format!("Failed to set permissions on temp file: {}", tmp_path.display()))
+        })
+        .and_then(|_| fs::rename(&tmp_path, target).with_context(|| format!("Failed to replace file: {}", target.display())));
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+
+    result
+}
+
+/// Build a sibling path for a temporary file used to atomically replace `path`.
+///
+/// Two concurrent writes can resolve to the same target (e.g. two symlinks pointing at
+/// the same file under `--jobs > 1`), so the PID alone isn't enough to keep their temp
+/// files from colliding; a per-write counter makes each one unique.
+fn temp_path_for(path: &Path) -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let unique = COUNTER.fetch_add(1, Ordering::Relaxed);
+
+    let mut tmp_name = OsString::from(".");
+    tmp_name.push(path.file_name().unwrap_or_default());
+    tmp_name.push(format!(".nocrlf-tmp-{}-{unique}", std::process::id()));
+
+    path.with_file_name(tmp_name)
+}
+
+/// Normalize every line ending in `content` (`\r\n`, lone `\r`, or lone `\n`) to `to`.
+fn normalize_line_endings(content: &[u8], to: LineEnding) -> Vec<u8> {
+    trace!("Normalizing line endings in {} byte buffer to {to:?}", content.len());
 
+    let target = to.as_bytes();
     let mut result = Vec::with_capacity(content.len());
     let mut i = 0;
 
     while i < content.len() {
-        if i + 1 < content.len() && content[i] == b'\r' && content[i + 1] == b'\n' {
-            // Found CRLF, replace with LF
-            result.push(b'\n');
-            i += 2;
-        } else {
-            // Regular character
-            result.push(content[i]);
-            i += 1;
+        match content[i] {
+            b'\r' => {
+                result.extend_from_slice(target);
+                i += if content.get(i + 1) == Some(&b'\n') { 2 } else { 1 };
+            }
+            b'\n' => {
+                result.extend_from_slice(target);
+                i += 1;
+            }
+            byte => {
+                result.push(byte);
+                i += 1;
+            }
         }
     }
 
-    trace!("Conversion complete, result size: {} bytes", result.len());
+    trace!("Normalization complete, result size: {} bytes", result.len());
     result
 }
 
@@ -202,7 +467,7 @@ mod tests {
     fn test_convert_crlf_to_lf() {
         let input = b"Hello\r\nWorld\r\nTest\r\n";
         let expected = b"Hello\nWorld\nTest\n";
-        let result = convert_crlf_to_lf(input);
+        let result = normalize_line_endings(input, LineEnding::Lf);
         assert_eq!(result, expected);
     }
 
@@ -210,7 +475,7 @@ mod tests {
     fn test_convert_no_crlf() {
         let input = b"Hello\nWorld\nTest\n";
         let expected = b"Hello\nWorld\nTest\n";
-        let result = convert_crlf_to_lf(input);
+        let result = normalize_line_endings(input, LineEnding::Lf);
         assert_eq!(result, expected);
     }
 
@@ -218,7 +483,98 @@ mod tests {
     fn test_convert_mixed() {
         let input = b"Hello\r\nWorld\nTest\r\n";
         let expected = b"Hello\nWorld\nTest\n";
-        let result = convert_crlf_to_lf(input);
+        let result = normalize_line_endings(input, LineEnding::Lf);
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_convert_lone_cr_to_lf() {
+        let input = b"Hello\rWorld\rTest\r";
+        let expected = b"Hello\nWorld\nTest\n";
+        let result = normalize_line_endings(input, LineEnding::Lf);
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_convert_all_three_styles_to_lf() {
+        let input = b"Hello\r\nWorld\rTest\n";
+        let expected = b"Hello\nWorld\nTest\n";
+        let result = normalize_line_endings(input, LineEnding::Lf);
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_convert_lf_to_crlf() {
+        let input = b"Hello\nWorld\nTest\n";
+        let expected = b"Hello\r\nWorld\r\nTest\r\n";
+        let result = normalize_line_endings(input, LineEnding::Crlf);
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_convert_to_cr() {
+        let input = b"Hello\r\nWorld\nTest\r";
+        let expected = b"Hello\rWorld\rTest\r";
+        let result = normalize_line_endings(input, LineEnding::Cr);
         assert_eq!(result, expected);
     }
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("no-crlf-test-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn no_op_filters() -> Filters {
+        Filters {
+            include: None,
+            exclude: None,
+            force_text: false,
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_process_file_converts_symlink_target_in_place() {
+        use std::os::unix::fs::symlink;
+
+        let dir = test_dir("symlink-target");
+        let target = dir.join("target.txt");
+        fs::write(&target, b"Hello\r\nWorld\r\n").unwrap();
+        let link = dir.join("link.txt");
+        symlink(&target, &link).unwrap();
+
+        let total_files = AtomicUsize::new(0);
+        let converted_files = AtomicUsize::new(0);
+        process_file(&link, false, false, LineEnding::Lf, &no_op_filters(), &total_files, &converted_files).unwrap();
+
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_link(&link).unwrap(), target);
+        assert_eq!(fs::read(&target).unwrap(), b"Hello\nWorld\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_write_atomic_cleans_up_temp_file_on_rename_failure() {
+        let dir = test_dir("write-atomic-cleanup");
+        // Writing the temp file succeeds (its parent, `dir`, exists), but renaming a
+        // regular file onto an existing directory fails, exercising the cleanup path.
+        let target = dir.join("target-is-a-dir");
+        fs::create_dir(&target).unwrap();
+        let metadata = fs::metadata(&dir).unwrap();
+
+        let result = write_atomic(&target, b"Hello\n", &metadata);
+        assert!(result.is_err());
+
+        let leftovers: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path() != target)
+            .collect();
+        assert!(leftovers.is_empty(), "temp file was not cleaned up: {leftovers:?}");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }